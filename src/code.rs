@@ -0,0 +1,356 @@
+use std::fmt::Write;
+
+pub type Instructions = Vec<u8>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum Opcode {
+    Constant,
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    True,
+    False,
+    Equal,
+    NotEqual,
+    GreaterThan,
+    Minus,
+    Bang,
+    JumpNotTruthy,
+    Jump,
+    Null,
+    GetGlobal,
+    SetGlobal,
+    GetLocal,
+    SetLocal,
+    GetBuiltin,
+    GetFree,
+    Call,
+    ReturnValue,
+    Return,
+    Closure,
+    CurrentClosure,
+}
+
+// Every opcode in declaration order, so a raw byte can be mapped back to its Opcode.
+// CurrentClosure must stay the last variant; test_opcodes_cover_enum relies on it
+const OPCODES: [Opcode; 27] = [
+    Opcode::Constant,
+    Opcode::Pop,
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::True,
+    Opcode::False,
+    Opcode::Equal,
+    Opcode::NotEqual,
+    Opcode::GreaterThan,
+    Opcode::Minus,
+    Opcode::Bang,
+    Opcode::JumpNotTruthy,
+    Opcode::Jump,
+    Opcode::Null,
+    Opcode::GetGlobal,
+    Opcode::SetGlobal,
+    Opcode::GetLocal,
+    Opcode::SetLocal,
+    Opcode::GetBuiltin,
+    Opcode::GetFree,
+    Opcode::Call,
+    Opcode::ReturnValue,
+    Opcode::Return,
+    Opcode::Closure,
+    Opcode::CurrentClosure,
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Definition {
+    pub name: &'static str,
+    pub operand_widths: &'static [usize],
+}
+
+impl Opcode {
+    #[must_use]
+    pub const fn definition(self) -> Definition {
+        let (name, operand_widths): (&'static str, &'static [usize]) = match self {
+            Self::Constant => ("OpConstant", &[2]),
+            Self::Pop => ("OpPop", &[]),
+            Self::Add => ("OpAdd", &[]),
+            Self::Sub => ("OpSub", &[]),
+            Self::Mul => ("OpMul", &[]),
+            Self::Div => ("OpDiv", &[]),
+            Self::True => ("OpTrue", &[]),
+            Self::False => ("OpFalse", &[]),
+            Self::Equal => ("OpEqual", &[]),
+            Self::NotEqual => ("OpNotEqual", &[]),
+            Self::GreaterThan => ("OpGreaterThan", &[]),
+            Self::Minus => ("OpMinus", &[]),
+            Self::Bang => ("OpBang", &[]),
+            Self::JumpNotTruthy => ("OpJumpNotTruthy", &[2]),
+            Self::Jump => ("OpJump", &[2]),
+            Self::Null => ("OpNull", &[]),
+            Self::GetGlobal => ("OpGetGlobal", &[2]),
+            Self::SetGlobal => ("OpSetGlobal", &[2]),
+            Self::GetLocal => ("OpGetLocal", &[1]),
+            Self::SetLocal => ("OpSetLocal", &[1]),
+            Self::GetBuiltin => ("OpGetBuiltin", &[1]),
+            Self::GetFree => ("OpGetFree", &[1]),
+            Self::Call => ("OpCall", &[1]),
+            Self::ReturnValue => ("OpReturnValue", &[]),
+            Self::Return => ("OpReturn", &[]),
+            // constant index of the function, number of free variables
+            Self::Closure => ("OpClosure", &[2, 1]),
+            Self::CurrentClosure => ("OpCurrentClosure", &[]),
+        };
+        Definition {
+            name,
+            operand_widths,
+        }
+    }
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        OPCODES
+            .get(usize::from(byte))
+            .copied()
+            .ok_or_else(|| format!("opcode {byte} undefined"))
+    }
+}
+
+/// Encodes a single instruction, writing every operand big-endian in the width
+/// given by the opcode's definition.
+#[must_use]
+pub fn make(op: Opcode, operands: &[usize]) -> Instructions {
+    let def = op.definition();
+    assert_eq!(
+        operands.len(),
+        def.operand_widths.len(),
+        "{} takes {} operands",
+        def.name,
+        def.operand_widths.len()
+    );
+    let instruction_len = 1 + def.operand_widths.iter().sum::<usize>();
+
+    let mut instruction = Vec::with_capacity(instruction_len);
+    instruction.push(op as u8);
+
+    for (operand, width) in operands.iter().zip(def.operand_widths) {
+        match width {
+            2 => {
+                let operand = u16::try_from(*operand).expect("operand does not fit in 2 bytes");
+                instruction.extend_from_slice(&operand.to_be_bytes());
+            }
+            1 => {
+                let operand = u8::try_from(*operand).expect("operand does not fit in 1 byte");
+                instruction.push(operand);
+            }
+            _ => unreachable!("unsupported operand width {width}"),
+        }
+    }
+
+    instruction
+}
+
+/// Decodes the operands that follow an opcode, returning them together with
+/// the number of bytes read.
+#[must_use]
+pub fn read_operands(def: &Definition, ins: &[u8]) -> (Vec<usize>, usize) {
+    let mut operands = Vec::with_capacity(def.operand_widths.len());
+    let mut offset = 0;
+
+    for width in def.operand_widths {
+        match width {
+            2 => operands.push(usize::from(read_u16(&ins[offset..]))),
+            1 => operands.push(usize::from(ins[offset])),
+            _ => unreachable!("unsupported operand width {width}"),
+        }
+        offset += width;
+    }
+
+    (operands, offset)
+}
+
+#[must_use]
+pub fn read_u16(ins: &[u8]) -> u16 {
+    u16::from_be_bytes([ins[0], ins[1]])
+}
+
+/// Renders instructions one per line as `<offset> <name> <operands>`.
+#[must_use]
+pub fn disassemble(ins: &[u8]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < ins.len() {
+        let op = match Opcode::try_from(ins[i]) {
+            Ok(op) => op,
+            Err(err) => {
+                writeln!(out, "ERROR: {err}").unwrap();
+                i += 1;
+                continue;
+            }
+        };
+
+        let def = op.definition();
+        let width: usize = def.operand_widths.iter().sum();
+        if ins.len() - (i + 1) < width {
+            writeln!(out, "ERROR: {} is missing operands", def.name).unwrap();
+            break;
+        }
+
+        let (operands, read) = read_operands(&def, &ins[i + 1..]);
+        writeln!(out, "{i:04} {}", fmt_instruction(&def, &operands)).unwrap();
+
+        i += 1 + read;
+    }
+
+    out
+}
+
+fn fmt_instruction(def: &Definition, operands: &[usize]) -> String {
+    let operand_count = def.operand_widths.len();
+
+    if operands.len() != operand_count {
+        return format!(
+            "ERROR: operand len {} does not match defined {}",
+            operands.len(),
+            operand_count
+        );
+    }
+
+    match operands {
+        [] => def.name.to_string(),
+        [a] => format!("{} {a}", def.name),
+        [a, b] => format!("{} {a} {b}", def.name),
+        _ => format!("ERROR: unhandled operand_count for {}", def.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make() {
+        let tests = vec![
+            (
+                Opcode::Constant,
+                vec![65534],
+                vec![Opcode::Constant as u8, 255, 254],
+            ),
+            (Opcode::Add, vec![], vec![Opcode::Add as u8]),
+            (
+                Opcode::GetLocal,
+                vec![255],
+                vec![Opcode::GetLocal as u8, 255],
+            ),
+            (
+                Opcode::GetBuiltin,
+                vec![255],
+                vec![Opcode::GetBuiltin as u8, 255],
+            ),
+            (
+                Opcode::Closure,
+                vec![65534, 255],
+                vec![Opcode::Closure as u8, 255, 254, 255],
+            ),
+        ];
+
+        for (op, operands, expected) in tests {
+            let instruction = make(op, &operands);
+            assert_eq!(
+                instruction, expected,
+                "instruction has wrong encoding. want={expected:?}, got={instruction:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_instructions_string() {
+        let instructions = [
+            make(Opcode::Add, &[]),
+            make(Opcode::GetLocal, &[1]),
+            make(Opcode::Constant, &[2]),
+            make(Opcode::Constant, &[65535]),
+            make(Opcode::Closure, &[65535, 255]),
+            make(Opcode::GetBuiltin, &[3]),
+        ]
+        .concat();
+
+        let expected = "0000 OpAdd
+0001 OpGetLocal 1
+0003 OpConstant 2
+0006 OpConstant 65535
+0009 OpClosure 65535 255
+0013 OpGetBuiltin 3
+";
+
+        assert_eq!(
+            disassemble(&instructions),
+            expected,
+            "instructions wrongly formatted"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_bad_input() {
+        let instructions = [vec![255], vec![Opcode::Constant as u8, 1]].concat();
+
+        let expected = "ERROR: opcode 255 undefined
+ERROR: OpConstant is missing operands
+";
+
+        assert_eq!(disassemble(&instructions), expected);
+    }
+
+    #[test]
+    fn test_read_operands() {
+        let tests = vec![
+            (Opcode::Constant, vec![65535], 2),
+            (Opcode::GetLocal, vec![255], 1),
+            (Opcode::Closure, vec![65535, 255], 3),
+        ];
+
+        for (op, operands, bytes_read) in tests {
+            let instruction = make(op, &operands);
+            let def = op.definition();
+
+            let (operands_read, n) = read_operands(&def, &instruction[1..]);
+            assert_eq!(n, bytes_read, "n wrong. want={bytes_read}, got={n}");
+            assert_eq!(operands_read, operands, "operands wrong");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "OpConstant takes 1 operands")]
+    fn test_make_missing_operand() {
+        let _ = make(Opcode::Constant, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "OpAdd takes 0 operands")]
+    fn test_make_extra_operand() {
+        let _ = make(Opcode::Add, &[1]);
+    }
+
+    #[test]
+    fn test_opcodes_cover_enum() {
+        assert_eq!(OPCODES.len(), Opcode::CurrentClosure as usize + 1);
+        for (i, op) in OPCODES.iter().enumerate() {
+            assert_eq!(*op as usize, i, "{op:?} is out of order in OPCODES");
+        }
+    }
+
+    #[test]
+    fn test_opcode_round_trip() {
+        for op in OPCODES {
+            assert_eq!(Opcode::try_from(op as u8), Ok(op));
+        }
+    }
+}
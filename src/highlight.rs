@@ -0,0 +1,294 @@
+use std::fmt::Write;
+
+use crate::lexer::Lexer;
+use crate::token::TokenType;
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Category {
+    Keyword,
+    Ident,
+    Literal,
+    Operator,
+    Delimiter,
+    Illegal,
+}
+
+impl Category {
+    #[must_use]
+    pub const fn of(kind: &TokenType) -> Option<Self> {
+        let category = match kind {
+            TokenType::Function
+            | TokenType::Let
+            | TokenType::True
+            | TokenType::False
+            | TokenType::If
+            | TokenType::Else
+            | TokenType::Return => Self::Keyword,
+            TokenType::Ident => Self::Ident,
+            TokenType::Int => Self::Literal,
+            TokenType::Assign
+            | TokenType::Plus
+            | TokenType::Minus
+            | TokenType::Bang
+            | TokenType::Asterisk
+            | TokenType::Slash
+            | TokenType::Lt
+            | TokenType::Gt
+            | TokenType::Eq
+            | TokenType::NotEq => Self::Operator,
+            TokenType::Comma
+            | TokenType::Semicolon
+            | TokenType::LParen
+            | TokenType::RParen
+            | TokenType::LBrace
+            | TokenType::RBrace => Self::Delimiter,
+            TokenType::Illegal => Self::Illegal,
+            TokenType::Eof => return None,
+        };
+        Some(category)
+    }
+
+    /// CSS class used for the category in HTML output.
+    #[must_use]
+    pub const fn class(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Ident => "ident",
+            Self::Literal => "literal",
+            Self::Operator => "operator",
+            Self::Delimiter => "delimiter",
+            Self::Illegal => "illegal",
+        }
+    }
+
+    const fn ansi(self) -> Option<&'static str> {
+        match self {
+            Self::Keyword => Some("\x1b[35m"),
+            Self::Literal => Some("\x1b[33m"),
+            Self::Operator => Some("\x1b[36m"),
+            Self::Illegal => Some("\x1b[4;31m"),
+            Self::Ident | Self::Delimiter => None,
+        }
+    }
+}
+
+/// Splits `source` into consecutive pieces, each either a token with its
+/// category or the text the lexer skipped between tokens. Concatenating the
+/// pieces gives back `source` unchanged.
+#[must_use]
+pub fn spans(source: &str) -> Vec<(Option<Category>, String)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut lexer = Lexer::new(source);
+    let mut spans = Vec::new();
+    let mut end = 0;
+
+    loop {
+        let tok = lexer.next_token();
+        let Some(category) = Category::of(&tok.kind) else {
+            break;
+        };
+
+        let start = lexer.position() - tok.literal.chars().count();
+        if start > end {
+            spans.push((None, chars[end..start].iter().collect()));
+        }
+        spans.push((Some(category), tok.literal));
+        end = lexer.position();
+    }
+
+    // Trailing whitespace, or whatever follows a NUL the lexer took for the end
+    if end < chars.len() {
+        spans.push((None, chars[end..].iter().collect()));
+    }
+
+    spans
+}
+
+/// Highlights `source` with ANSI escape codes for a terminal.
+#[must_use]
+pub fn to_ansi(source: &str) -> String {
+    let mut out = String::new();
+
+    for (category, text) in spans(source) {
+        // Illegal tokens, and text past a NUL, can hold control characters
+        // that would act on the terminal instead of being shown
+        let text = escape_controls(&text);
+        match category.and_then(Category::ansi) {
+            Some(color) => write!(out, "{color}{text}{ANSI_RESET}").unwrap(),
+            None => out.push_str(&text),
+        }
+    }
+
+    out
+}
+
+/// Shows control characters other than tabs and line breaks in caret
+/// notation (`^[` for ESC), or as a Unicode escape for C1 controls.
+fn escape_controls(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' | '\n' | '\r' => out.push(c),
+            '\0'..='\x1f' | '\x7f' => {
+                out.push('^');
+                out.push(char::from(u8::try_from(c).unwrap() ^ 0x40));
+            }
+            _ if c.is_control() => write!(out, "{}", c.escape_unicode()).unwrap(),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Highlights `source` as HTML, wrapping every token in a span whose class
+/// names its category.
+#[must_use]
+pub fn to_html(source: &str) -> String {
+    let mut out = String::new();
+
+    for (category, text) in spans(source) {
+        match category {
+            Some(category) => write!(
+                out,
+                "<span class=\"{}\">{}</span>",
+                category.class(),
+                escape_html(&text)
+            )
+            .unwrap(),
+            None => out.push_str(&escape_html(&text)),
+        }
+    }
+
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUTS: [&str; 7] = [
+        "let five = 5;\nlet add = fn(x, y) {\n\tx + y;\r\n};\n",
+        "   leading and trailing   \n\n",
+        "",
+        "if (5 < 10) { return true; } else { return false != !true; }",
+        "let é = \"ü\" @ 1;",
+        "let x = 1;\0 after nul",
+        "let \x1b[2J = 1;\x07\u{9b}",
+    ];
+
+    fn strip_ansi(text: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    fn strip_tags(html: &str) -> String {
+        let mut out = String::new();
+        let mut in_tag = false;
+        for c in html.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                _ if !in_tag => out.push(c),
+                _ => {}
+            }
+        }
+        out.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&amp;", "&")
+    }
+
+    #[test]
+    fn test_spans_keep_source() {
+        for input in INPUTS {
+            let joined: String = spans(input).into_iter().map(|(_, text)| text).collect();
+            assert_eq!(joined, input);
+            assert_eq!(strip_ansi(&to_ansi(input)), escape_controls(input));
+            assert_eq!(strip_tags(&to_html(input)), input);
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let expected = vec![
+            (Some(Category::Keyword), "let"),
+            (None, " "),
+            (Some(Category::Ident), "x"),
+            (None, " "),
+            (Some(Category::Operator), "=="),
+            (None, "  "),
+            (Some(Category::Literal), "10"),
+            (Some(Category::Delimiter), ";"),
+            (Some(Category::Illegal), "é"),
+            (None, "\n"),
+        ];
+
+        let got = spans("let x ==  10;é\n");
+        let got: Vec<(Option<Category>, &str)> = got
+            .iter()
+            .map(|(category, text)| (*category, text.as_str()))
+            .collect();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(
+            to_html("if (a < b) {}"),
+            concat!(
+                "<span class=\"keyword\">if</span> ",
+                "<span class=\"delimiter\">(</span>",
+                "<span class=\"ident\">a</span> ",
+                "<span class=\"operator\">&lt;</span> ",
+                "<span class=\"ident\">b</span>",
+                "<span class=\"delimiter\">)</span> ",
+                "<span class=\"delimiter\">{</span>",
+                "<span class=\"delimiter\">}</span>",
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_ansi_escapes_control_characters() {
+        let highlighted = to_ansi("let \x1b[2J = 1;\x07\u{9b}\0\x1b]0;x");
+
+        assert_eq!(strip_ansi(&highlighted), "let ^[[2J = 1;^G\\u{9b}^@^[]0;x");
+        assert_eq!(
+            highlighted.matches('\x1b').count(),
+            highlighted.matches(ANSI_RESET).count() * 2,
+            "raw ESC outside of colour codes. got={highlighted:?}"
+        );
+    }
+
+    #[test]
+    fn test_to_ansi() {
+        assert_eq!(
+            to_ansi("let x = 5;"),
+            "\x1b[35mlet\x1b[0m x \x1b[36m=\x1b[0m \x1b[33m5\x1b[0m;"
+        );
+    }
+}
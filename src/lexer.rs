@@ -2,7 +2,9 @@ use crate::token::{lookup_ident, Token, TokenType};
 
 #[derive(Debug)]
 pub struct Lexer {
-    input: String,
+    // Indexed by character rather than byte, so non-ASCII input lexes as Illegal
+    // tokens instead of splitting a code point
+    input: Vec<char>,
     position: usize,
     read_position: usize,
     char: char,
@@ -12,10 +14,10 @@ impl Lexer {
     #[must_use]
     pub fn new(input: &str) -> Self {
         let mut a = Self {
-            input: input.to_string(),
+            input: input.chars().collect(),
             position: 0,
             read_position: 0,
-            char: input.chars().nth(0).unwrap(),
+            char: '\0',
         };
         a.read_char();
         a
@@ -26,7 +28,7 @@ impl Lexer {
         if self.read_position >= self.input.len() {
             self.char = '\0';
         } else {
-            self.char = self.input.chars().nth(self.read_position).unwrap();
+            self.char = self.input[self.read_position];
         }
         self.position = self.read_position;
         self.read_position += 1;
@@ -91,7 +93,7 @@ impl Lexer {
             self.read_char();
         }
         // dbg!(self.input[start_position..self.position].to_string());
-        self.input[start_position..self.position].to_string()
+        self.input[start_position..self.position].iter().collect()
     }
 
     fn read_number(&mut self) -> String {
@@ -99,7 +101,7 @@ impl Lexer {
         while is_digit(self.char) {
             self.read_char();
         }
-        self.input[start_position..self.position].to_string()
+        self.input[start_position..self.position].iter().collect()
     }
 
     fn skip_whitespace(&mut self) {
@@ -109,11 +111,13 @@ impl Lexer {
     }
 
     fn peek_char(&self) -> Option<char> {
-        if self.read_position >= self.input.len() {
-            None
-        } else {
-            Some(self.input.chars().nth(self.read_position).unwrap())
-        }
+        self.input.get(self.read_position).copied()
+    }
+
+    /// Character offset just past the most recently read token.
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
     }
 
     fn char_to_string(&self) -> String {
@@ -261,6 +265,29 @@ mod tests {
         ];
         run_tests(&tests, &mut lexer);
     }
+
+    #[test]
+    fn test_next_token_with_empty_input() {
+        let mut lexer = Lexer::new("");
+        run_tests(&[(TokenType::Eof, "\0")], &mut lexer);
+    }
+
+    #[test]
+    fn test_next_token_with_non_ascii_input() {
+        let input = "let é = ü;";
+        let mut lexer = Lexer::new(input);
+
+        let tests = vec![
+            (TokenType::Let, "let"),
+            (TokenType::Illegal, "é"),
+            (TokenType::Assign, "="),
+            (TokenType::Illegal, "ü"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, "\0"),
+        ];
+        run_tests(&tests, &mut lexer);
+    }
+
     fn run_tests(tests: &[(TokenType, &str)], lexer: &mut Lexer) {
         for (i, (expected_type, expected_literal)) in tests.iter().enumerate() {
             let tok = lexer.next_token();
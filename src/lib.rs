@@ -0,0 +1,10 @@
+pub mod ast;
+pub mod code;
+pub mod highlight;
+pub mod lexer;
+#[cfg(all(unix, feature = "line-editor"))]
+pub mod line_editor;
+pub mod parser;
+pub mod repl;
+pub mod symbol_table;
+pub mod token;
@@ -0,0 +1,736 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::raw::c_int;
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = ".monkey_history";
+const HISTORY_LIMIT: usize = 1000;
+
+const STDIN_FILENO: c_int = 0;
+const STDOUT_FILENO: c_int = 1;
+const TCSADRAIN: c_int = 1;
+
+// The layout of struct termios differs between platforms, but it is only ever
+// handed back to libc, so an opaque buffer larger than any of them will do
+#[derive(Clone, Copy)]
+#[repr(C, align(8))]
+struct Termios([u8; 256]);
+
+extern "C" {
+    fn isatty(fd: c_int) -> c_int;
+    fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+    fn tcsetattr(fd: c_int, optional_actions: c_int, termios: *const Termios) -> c_int;
+    fn cfmakeraw(termios: *mut Termios);
+}
+
+/// Puts the terminal in raw mode for as long as it is alive.
+struct RawMode {
+    original: Termios,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let mut original = Termios([0; 256]);
+        // SAFETY: the buffer outlives the call and is larger than struct termios
+        if unsafe { tcgetattr(STDIN_FILENO, &raw mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        // SAFETY: raw holds the attributes tcgetattr just filled in
+        unsafe { cfmakeraw(&raw mut raw) };
+        // SAFETY: as above
+        if unsafe { tcsetattr(STDIN_FILENO, TCSADRAIN, &raw const raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: original holds the attributes read in enable
+        unsafe { tcsetattr(STDIN_FILENO, TCSADRAIN, &raw const self.original) };
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadLine {
+    Line(String),
+    Interrupted,
+    Eof,
+}
+
+/// Line editor for interactive terminals with emacs-style keys, history
+/// persisted in `~/.monkey_history`, Ctrl-R search and tab completion.
+pub struct LineEditor {
+    history: History,
+}
+
+impl LineEditor {
+    /// Returns `None` unless both stdin and stdout are terminals, in which case
+    /// the caller should read plain lines instead.
+    #[must_use]
+    pub fn new() -> Option<Self> {
+        // SAFETY: isatty only inspects the descriptors
+        let is_tty = unsafe { isatty(STDIN_FILENO) == 1 && isatty(STDOUT_FILENO) == 1 };
+        if !is_tty {
+            return None;
+        }
+
+        let path = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        Some(Self {
+            history: History::load(path),
+        })
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        self.history.add(line);
+    }
+
+    /// Reads one line, offering `completions` on tab.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the terminal cannot be switched to raw mode or if reading or
+    /// writing the terminal fails.
+    pub fn read_line(&mut self, prompt: &str, completions: &[&str]) -> io::Result<ReadLine> {
+        let raw_mode = RawMode::enable()?;
+        let mut out = io::stdout().lock();
+
+        let result = self.edit(&mut io::stdin().lock(), &mut out, prompt, completions);
+
+        write!(out, "\r\n")?;
+        out.flush()?;
+        drop(raw_mode);
+        result
+    }
+
+    fn edit<R: Read, W: Write>(
+        &self,
+        input: &mut R,
+        out: &mut W,
+        prompt: &str,
+        completions: &[&str],
+    ) -> io::Result<ReadLine> {
+        let input = &mut KeyInput::new(input);
+        let mut line = LineState::default();
+        // Position while browsing history; entries.len() is the line being typed
+        let mut browsing = self.history.entries.len();
+        let mut typed = String::new();
+
+        loop {
+            refresh(out, prompt, &line)?;
+
+            let Some(key) = read_key(input)? else {
+                return Ok(ReadLine::Eof);
+            };
+
+            match key {
+                Key::Enter => return Ok(ReadLine::Line(line.to_string())),
+                Key::Ctrl('c') => {
+                    write!(out, "^C")?;
+                    return Ok(ReadLine::Interrupted);
+                }
+                Key::Ctrl('d') if line.buf.is_empty() => return Ok(ReadLine::Eof),
+                Key::Ctrl('d') | Key::Delete => line.delete(),
+                Key::Backspace => line.backspace(),
+                Key::Char(c) => line.insert(c),
+                Key::Left | Key::Ctrl('b') => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => line.cursor = (line.cursor + 1).min(line.buf.len()),
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.buf.len(),
+                Key::Ctrl('k') => line.buf.truncate(line.cursor),
+                Key::Ctrl('u') => {
+                    line.buf.drain(..line.cursor);
+                    line.cursor = 0;
+                }
+                Key::Up | Key::Ctrl('p') if browsing > 0 => {
+                    if browsing == self.history.entries.len() {
+                        typed = line.to_string();
+                    }
+                    browsing -= 1;
+                    line.set(&self.history.entries[browsing]);
+                }
+                Key::Down | Key::Ctrl('n') if browsing < self.history.entries.len() => {
+                    browsing += 1;
+                    line.set(self.history.entries.get(browsing).unwrap_or(&typed));
+                }
+                Key::Tab => complete(out, &mut line, completions)?,
+                Key::Ctrl('r') => match self.reverse_search(input, out)? {
+                    Search::Submit(found) => return Ok(ReadLine::Line(found)),
+                    Search::Edit(found) => line.set(&found),
+                    Search::Cancel => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn reverse_search<R: Read, W: Write>(
+        &self,
+        input: &mut KeyInput<R>,
+        out: &mut W,
+    ) -> io::Result<Search> {
+        let entries = &self.history.entries;
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+
+        loop {
+            let current = found.map_or("", |i| entries[i].as_str());
+            write!(out, "\r(reverse-i-search)`{query}': {current}\x1b[K")?;
+            out.flush()?;
+
+            let Some(key) = read_key(input)? else {
+                return Ok(Search::Cancel);
+            };
+
+            match key {
+                Key::Char(c) => {
+                    query.push(c);
+                    let before = found.map_or(entries.len(), |i| i + 1);
+                    found = self.history.search(&query, before);
+                }
+                Key::Backspace => {
+                    query.pop();
+                    found = self.history.search(&query, entries.len());
+                }
+                Key::Ctrl('r') => {
+                    let before = found.unwrap_or(entries.len());
+                    found = self.history.search(&query, before).or(found);
+                }
+                Key::Enter => {
+                    return Ok(found.map_or(Search::Cancel, |i| Search::Submit(entries[i].clone())))
+                }
+                Key::Ctrl('g' | 'c') | Key::Escape => return Ok(Search::Cancel),
+                _ => return Ok(found.map_or(Search::Cancel, |i| Search::Edit(entries[i].clone()))),
+            }
+        }
+    }
+}
+
+enum Search {
+    Submit(String),
+    Edit(String),
+    Cancel,
+}
+
+fn refresh<W: Write>(out: &mut W, prompt: &str, line: &LineState) -> io::Result<()> {
+    write!(out, "\r{prompt}{line}\x1b[K")?;
+    let tail = line.buf.len() - line.cursor;
+    if tail > 0 {
+        write!(out, "\x1b[{tail}D")?;
+    }
+    out.flush()
+}
+
+fn complete<W: Write>(out: &mut W, line: &mut LineState, completions: &[&str]) -> io::Result<()> {
+    let prefix = line.word_before_cursor();
+    if prefix.is_empty() {
+        return Ok(());
+    }
+
+    let matches = candidates(&prefix, completions);
+    let extension = common_prefix(&matches);
+
+    if matches.is_empty() {
+        write!(out, "\x07")?;
+    } else if extension.len() > prefix.len() {
+        line.insert_str(&extension[prefix.len()..]);
+    } else if matches.len() > 1 {
+        write!(out, "\r\n{}\r\n", matches.join("  "))?;
+    }
+    Ok(())
+}
+
+fn candidates<'a>(prefix: &str, completions: &[&'a str]) -> Vec<&'a str> {
+    let mut matches: Vec<&str> = completions
+        .iter()
+        .copied()
+        .filter(|word| word.starts_with(prefix))
+        .collect();
+    matches.sort_unstable();
+    matches.dedup();
+    matches
+}
+
+fn common_prefix(words: &[&str]) -> String {
+    let Some((first, rest)) = words.split_first() else {
+        return String::new();
+    };
+
+    let mut len = first.len();
+    for word in rest {
+        len = first
+            .char_indices()
+            .zip(word.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+#[derive(Debug, Default)]
+struct LineState {
+    buf: Vec<char>,
+    cursor: usize,
+}
+
+impl LineState {
+    fn insert(&mut self, c: char) {
+        self.buf.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn insert_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.insert(c);
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.buf.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.buf.len() {
+            self.buf.remove(self.cursor);
+        }
+    }
+
+    fn set(&mut self, s: &str) {
+        self.buf = s.chars().collect();
+        self.cursor = self.buf.len();
+    }
+
+    // Completion works on identifiers, which the lexer builds from letters and _
+    fn word_before_cursor(&self) -> String {
+        let start = self.buf[..self.cursor]
+            .iter()
+            .rposition(|c| !(c.is_ascii_alphabetic() || *c == '_'))
+            .map_or(0, |i| i + 1);
+        self.buf[start..self.cursor].iter().collect()
+    }
+}
+
+impl std::fmt::Display for LineState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.buf.iter().try_for_each(|c| write!(f, "{c}"))
+    }
+}
+
+#[derive(Debug, Default)]
+struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    fn load(path: Option<PathBuf>) -> Self {
+        let mut entries: Vec<String> = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().map(ToString::to_string).collect())
+            .unwrap_or_default();
+
+        let overflow = entries.len().saturating_sub(HISTORY_LIMIT);
+        entries.drain(..overflow);
+
+        // Entries are appended as they are typed, so trim the file here to keep
+        // it from growing without bound
+        if overflow > 0 {
+            if let Some(path) = &path {
+                let mut contents = entries.join("\n");
+                contents.push('\n');
+                let _ = fs::write(path, contents);
+            }
+        }
+
+        Self { entries, path }
+    }
+
+    fn add(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+
+        self.entries.push(line.to_string());
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+
+        // History is a convenience, so failing to persist it is not an error
+        if let Some(path) = &self.path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+
+    /// Finds the newest entry before index `before` that contains `query`.
+    fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Escape,
+    Unknown,
+}
+
+/// Terminal input that can put back one byte. A lone ESC is only known to be
+/// the Escape key once the byte after it has been read, and that byte starts
+/// the next key.
+struct KeyInput<R> {
+    inner: R,
+    pushback: Option<u8>,
+}
+
+impl<R: Read> KeyInput<R> {
+    const fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pushback: None,
+        }
+    }
+}
+
+impl<R: Read> Read for KeyInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (buf.first_mut(), self.pushback.take()) {
+            (Some(first), Some(byte)) => {
+                *first = byte;
+                Ok(1)
+            }
+            (_, pushback) => {
+                self.pushback = pushback;
+                self.inner.read(buf)
+            }
+        }
+    }
+}
+
+fn read_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn read_key<R: Read>(input: &mut KeyInput<R>) -> io::Result<Option<Key>> {
+    let Some(byte) = read_byte(input)? else {
+        return Ok(None);
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        8 | 127 => Key::Backspace,
+        27 => read_escape(input)?,
+        1..=26 => Key::Ctrl(char::from(b'a' + byte - 1)),
+        b' '..=b'~' => Key::Char(char::from(byte)),
+        0xc0.. => read_utf8(input, byte)?,
+        _ => Key::Unknown,
+    };
+    Ok(Some(key))
+}
+
+fn read_escape<R: Read>(input: &mut KeyInput<R>) -> io::Result<Key> {
+    let is_csi = match read_byte(input)? {
+        Some(b'[') => true,
+        Some(b'O') => false,
+        // Esc pressed on its own, or Alt held with another key
+        next => {
+            input.pushback = next;
+            return Ok(Key::Escape);
+        }
+    };
+
+    // A CSI sequence is parameter and intermediate bytes ended by a final byte
+    // in 0x40..=0x7e; ESC O is followed by the final byte alone
+    let mut params = Vec::new();
+    let final_byte = loop {
+        match read_byte(input)? {
+            Some(byte @ 0x40..=0x7e) => break byte,
+            Some(byte @ 0x20..=0x3f) if is_csi => params.push(byte),
+            _ => return Ok(Key::Unknown),
+        }
+    };
+
+    let key = match (params.as_slice(), final_byte) {
+        ([], b'A') => Key::Up,
+        ([], b'B') => Key::Down,
+        ([], b'C') => Key::Right,
+        ([], b'D') => Key::Left,
+        ([], b'H') | (b"1" | b"7", b'~') => Key::Home,
+        ([], b'F') | (b"4" | b"8", b'~') => Key::End,
+        (b"3", b'~') => Key::Delete,
+        _ => Key::Unknown,
+    };
+    Ok(key)
+}
+
+fn read_utf8<R: Read>(input: &mut R, lead: u8) -> io::Result<Key> {
+    let len = match lead {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        _ => 2,
+    };
+
+    let mut bytes = vec![lead];
+    for _ in 1..len {
+        match read_byte(input)? {
+            Some(byte) => bytes.push(byte),
+            None => return Ok(Key::Unknown),
+        }
+    }
+
+    Ok(std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|s| s.chars().next())
+        .map_or(Key::Unknown, Key::Char))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(input: &[u8]) -> Vec<Key> {
+        let mut input = KeyInput::new(input);
+        let mut keys = Vec::new();
+        while let Some(key) = read_key(&mut input).unwrap() {
+            keys.push(key);
+        }
+        keys
+    }
+
+    fn editor(history: &[&str]) -> LineEditor {
+        LineEditor {
+            history: History {
+                entries: history.iter().map(ToString::to_string).collect(),
+                path: None,
+            },
+        }
+    }
+
+    fn edit(editor: &LineEditor, input: &[u8], completions: &[&str]) -> ReadLine {
+        let mut input = input;
+        editor
+            .edit(&mut input, &mut io::sink(), "> ", completions)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_key() {
+        let input = b"a\x01\r\x7f\t\x1b[A\x1b[B\x1b[C\x1b[D\x1b[3~\x1bOH\xc3\xa4";
+        assert_eq!(
+            keys(input),
+            vec![
+                Key::Char('a'),
+                Key::Ctrl('a'),
+                Key::Enter,
+                Key::Backspace,
+                Key::Tab,
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left,
+                Key::Delete,
+                Key::Home,
+                Key::Char('ä'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_key_with_modified_keys() {
+        // Ctrl-Right, Shift-F1 and Alt-Up carry parameters but no tilde
+        assert_eq!(
+            keys(b"\x1b[1;5Ca\r"),
+            vec![Key::Unknown, Key::Char('a'), Key::Enter]
+        );
+        assert_eq!(
+            keys(b"\x1b[1;2Pb\x1b[1;3A\r"),
+            vec![Key::Unknown, Key::Char('b'), Key::Unknown, Key::Enter]
+        );
+    }
+
+    #[test]
+    fn test_read_key_after_escape() {
+        assert_eq!(
+            keys(b"\x1ba\r"),
+            vec![Key::Escape, Key::Char('a'), Key::Enter]
+        );
+        assert_eq!(keys(b"\x1b\x1b[A"), vec![Key::Escape, Key::Up]);
+        assert_eq!(keys(b"\x1b"), vec![Key::Escape]);
+    }
+
+    #[test]
+    fn test_editing_keys() {
+        let tests: Vec<(&[u8], &str)> = vec![
+            (b"let x\r", "let x"),
+            (b"let x\x7f\x7fy\r", "lety"),
+            (b"et x\x01l\r", "let x"),
+            (b"let x\x1b[D\x1b[D\x0b\r", "let"),
+            (b"let x\x1b[D\x15\r", "x"),
+            (b"ab\x1b[D\x04\r", "a"),
+            (b"le\x1bt\r", "let"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                edit(&editor(&[]), input, &[]),
+                ReadLine::Line(expected.to_string()),
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_eof_and_interrupt() {
+        assert_eq!(edit(&editor(&[]), b"", &[]), ReadLine::Eof);
+        assert_eq!(edit(&editor(&[]), b"\x04", &[]), ReadLine::Eof);
+        assert_eq!(edit(&editor(&[]), b"let\x03", &[]), ReadLine::Interrupted);
+    }
+
+    #[test]
+    fn test_history_navigation() {
+        let editor = editor(&["let a = 1;", "let b = 2;"]);
+
+        let tests: Vec<(&[u8], &str)> = vec![
+            (b"\x1b[A\r", "let b = 2;"),
+            (b"\x1b[A\x1b[A\r", "let a = 1;"),
+            (b"\x1b[A\x1b[A\x1b[A\r", "let a = 1;"),
+            (b"x\x1b[A\x1b[B\r", "x"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                edit(&editor, input, &[]),
+                ReadLine::Line(expected.to_string()),
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reverse_search() {
+        let editor = editor(&["let add = fn(a, b) { a + b };", "let x = 5;", "add(1, 2)"]);
+
+        let tests: Vec<(&[u8], ReadLine)> = vec![
+            (b"\x12add\r", ReadLine::Line("add(1, 2)".to_string())),
+            (
+                b"\x12add\x12\r",
+                ReadLine::Line("let add = fn(a, b) { a + b };".to_string()),
+            ),
+            (b"\x12x =\x1b[C\r", ReadLine::Line("let x = 5;".to_string())),
+            (b"y\x12x\x07\r", ReadLine::Line("y".to_string())),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(edit(&editor, input, &[]), expected, "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_tab_completion() {
+        let completions = ["let", "fn", "return", "result", "five"];
+
+        let tests: Vec<(&[u8], &str)> = vec![
+            (b"l\t x\r", "let x"),
+            (b"re\t\r", "re"),
+            (b"res\t\r", "result"),
+            (b"add(fi\t)\r", "add(five)"),
+            (b"zz\t\r", "zz"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                edit(&editor(&[]), input, &completions),
+                ReadLine::Line(expected.to_string()),
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        assert_eq!(common_prefix(&[]), "");
+        assert_eq!(common_prefix(&["return"]), "return");
+        assert_eq!(common_prefix(&["return", "result"]), "re");
+        assert_eq!(common_prefix(&["five", "fn"]), "f");
+        assert_eq!(common_prefix(&["let", "if"]), "");
+    }
+
+    #[test]
+    fn test_history_add() {
+        let mut history = History::default();
+        history.add("let a = 1;");
+        history.add("let a = 1;");
+        history.add("   ");
+        history.add("a");
+
+        assert_eq!(history.entries, vec!["let a = 1;", "a"]);
+        assert_eq!(history.search("a", 2), Some(1));
+        assert_eq!(history.search("let", 2), Some(0));
+        assert_eq!(history.search("let", 0), None);
+    }
+
+    #[test]
+    fn test_history_persists() {
+        let path = std::env::temp_dir().join(format!("monkey_history_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut history = History::load(Some(path.clone()));
+        history.add("let a = 1;");
+        history.add("a");
+
+        let reloaded = History::load(Some(path.clone()));
+        assert_eq!(reloaded.entries, vec!["let a = 1;", "a"]);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_history_file_is_trimmed_on_load() {
+        let path = std::env::temp_dir().join(format!("monkey_history_trim_{}", std::process::id()));
+        let lines: Vec<String> = (0..HISTORY_LIMIT + 5).map(|i| format!("{i};")).collect();
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let history = History::load(Some(path.clone()));
+        assert_eq!(history.entries.len(), HISTORY_LIMIT);
+        assert_eq!(history.entries[0], "5;");
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), HISTORY_LIMIT);
+        assert_eq!(contents.lines().next(), Some("5;"));
+
+        fs::remove_file(path).unwrap();
+    }
+}
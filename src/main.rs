@@ -1,22 +1,273 @@
 //https://edu.anarcho-copy.org/Programming%20Languages/Go/writing%20an%20INTERPRETER%20in%20go.pdf
 //44
-use std::io::{self, BufReader, BufWriter};
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process::ExitCode;
 
-pub mod ast;
-pub mod lexer;
-pub mod parser;
-pub mod repl;
-pub mod token;
+use interpreter::lexer::Lexer;
+use interpreter::parser::Parser;
+use interpreter::{highlight, repl};
 
-fn main() {
-    println!("Hello! This is the Monkey programming language!");
-    println!("Feel free to type in commands");
+const USAGE: &str = "usage: interpreter [command]
 
-    let stdin = io::stdin();
-    let stdout = io::stdout();
+commands:
+    repl            start the interactive prompt (default)
+    tokens <file>   print the tokens of a file
+    ast <file>      print the parsed statements of a file
+    check <file>    report parse errors in a file
+    highlight [--html] <file>
+                    print a file with syntax highlighting, as ANSI colours
+                    or as HTML spans
 
-    let input = BufReader::new(stdin.lock());
-    let output = BufWriter::new(stdout.lock());
+<file> may be - to read from stdin";
 
-    repl::start(input, output);
+// Exit codes follow sysexits.h so scripts can tell failures apart
+const EXIT_USAGE: u8 = 64;
+const EXIT_PARSE_ERROR: u8 = 65;
+const EXIT_NO_INPUT: u8 = 66;
+const EXIT_IO_ERROR: u8 = 74;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Repl,
+    Tokens(String),
+    Ast(String),
+    Check(String),
+    Highlight { path: String, html: bool },
+    Help,
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let Some(command) = args.first() else {
+        return Ok(Command::Repl);
+    };
+
+    let file = || match &args[1..] {
+        [file] => Ok(file.clone()),
+        [] => Err(format!("{command}: missing <file> argument")),
+        _ => Err(format!("{command}: expected a single <file> argument")),
+    };
+
+    match command.as_str() {
+        "repl" if args.len() == 1 => Ok(Command::Repl),
+        "repl" => Err("repl: unexpected arguments".to_string()),
+        "tokens" => file().map(Command::Tokens),
+        "ast" => file().map(Command::Ast),
+        "check" => file().map(Command::Check),
+        "highlight" => match &args[1..] {
+            [flag, path] if flag == "--html" => Ok(Command::Highlight {
+                path: path.clone(),
+                html: true,
+            }),
+            [flag] if flag == "--html" => Err(format!("{command}: missing <file> argument")),
+            _ => file().map(|path| Command::Highlight { path, html: false }),
+        },
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        _ => Err(format!("unknown command {command:?}")),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    match command {
+        Command::Repl => match check_output(run_repl()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(code) => code,
+        },
+        Command::Help => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Command::Tokens(path) => with_source(&path, print_tokens),
+        Command::Ast(path) => with_source(&path, |source| print_ast(&path, source)),
+        Command::Check(path) => with_source(&path, |source| check(&path, source)),
+        Command::Highlight { path, html } => with_source(&path, |source| {
+            let highlighted = if html {
+                highlight::to_html(source)
+            } else {
+                highlight::to_ansi(source)
+            };
+
+            let mut out = io::stdout().lock();
+            let written = out
+                .write_all(highlighted.as_bytes())
+                .and_then(|()| out.flush());
+            match check_output(written) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(code) => code,
+            }
+        }),
+    }
+}
+
+fn run_repl() -> io::Result<()> {
+    let mut output = BufWriter::new(io::stdout().lock());
+
+    writeln!(output, "Hello! This is the Monkey programming language!")?;
+    writeln!(output, "Feel free to type in commands")?;
+    output.flush()?;
+
+    #[cfg(all(unix, feature = "line-editor"))]
+    if let Some(mut editor) = interpreter::line_editor::LineEditor::new() {
+        return repl::start_with_editor(&mut editor, output);
+    }
+
+    let input = BufReader::new(io::stdin().lock());
+    repl::start(input, output)
+}
+
+fn with_source(path: &str, f: impl FnOnce(&str) -> ExitCode) -> ExitCode {
+    match read_source(path) {
+        Ok(source) => f(&source),
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            ExitCode::from(EXIT_NO_INPUT)
+        }
+    }
+}
+
+fn read_source(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn print_tokens(source: &str) -> ExitCode {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written = repl::write_tokens(source, &mut out).and_then(|()| out.flush());
+    match check_output(written) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(code) => code,
+    }
+}
+
+fn print_ast(path: &str, source: &str) -> ExitCode {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written = repl::write_ast(&program, &mut out).and_then(|()| out.flush());
+    if let Err(code) = check_output(written) {
+        return code;
+    }
+
+    report_parse_errors(path, parser.errors())
+}
+
+// A reader closing the pipe early, like `| head`, is not an error worth reporting
+fn check_output(result: io::Result<()>) -> Result<(), ExitCode> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        Err(err) => {
+            eprintln!("stdout: {err}");
+            Err(ExitCode::from(EXIT_IO_ERROR))
+        }
+    }
+}
+
+fn check(path: &str, source: &str) -> ExitCode {
+    let mut parser = Parser::new(Lexer::new(source));
+    parser.parse_program();
+
+    report_parse_errors(path, parser.errors())
+}
+
+fn report_parse_errors(path: &str, errors: &[String]) -> ExitCode {
+    if errors.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    for err in errors {
+        eprintln!("{path}: {err}");
+    }
+    ExitCode::from(EXIT_PARSE_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_check_output() {
+        assert_eq!(check_output(Ok(())), Ok(()));
+        assert_eq!(check_output(Err(io::ErrorKind::BrokenPipe.into())), Ok(()));
+        assert_eq!(
+            check_output(Err(io::ErrorKind::PermissionDenied.into())),
+            Err(ExitCode::from(EXIT_IO_ERROR))
+        );
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let tests = vec![
+            (vec![], Ok(Command::Repl)),
+            (vec!["repl"], Ok(Command::Repl)),
+            (
+                vec!["tokens", "first.monkey"],
+                Ok(Command::Tokens("first.monkey".to_string())),
+            ),
+            (vec!["ast", "-"], Ok(Command::Ast("-".to_string()))),
+            (
+                vec!["check", "first.monkey"],
+                Ok(Command::Check("first.monkey".to_string())),
+            ),
+            (
+                vec!["highlight", "first.monkey"],
+                Ok(Command::Highlight {
+                    path: "first.monkey".to_string(),
+                    html: false,
+                }),
+            ),
+            (
+                vec!["highlight", "--html", "first.monkey"],
+                Ok(Command::Highlight {
+                    path: "first.monkey".to_string(),
+                    html: true,
+                }),
+            ),
+            (
+                vec!["highlight", "--html"],
+                Err("highlight: missing <file> argument".to_string()),
+            ),
+            (vec!["--help"], Ok(Command::Help)),
+            (
+                vec!["tokens"],
+                Err("tokens: missing <file> argument".to_string()),
+            ),
+            (
+                vec!["check", "a.monkey", "b.monkey"],
+                Err("check: expected a single <file> argument".to_string()),
+            ),
+            (
+                vec!["repl", "a.monkey"],
+                Err("repl: unexpected arguments".to_string()),
+            ),
+            (
+                vec!["frobnicate"],
+                Err("unknown command \"frobnicate\"".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(parse_args(&args(&input)), expected, "args: {input:?}");
+        }
+    }
 }
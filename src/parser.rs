@@ -91,7 +91,7 @@ impl Parser {
             return None;
         }
 
-        while !self.cur_token_is(&TokenType::Semicolon) {
+        while !self.cur_token_is(&TokenType::Semicolon) && !self.cur_token_is(&TokenType::Eof) {
             self.next_token();
         }
 
@@ -114,7 +114,7 @@ impl Parser {
         self.next_token();
 
 
-        while !self.cur_token_is(&TokenType::Semicolon) {
+        while !self.cur_token_is(&TokenType::Semicolon) && !self.cur_token_is(&TokenType::Eof) {
             self.next_token();
         }
 
@@ -187,6 +187,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_statements_without_trailing_semicolon() {
+        for input in ["let x = 5", "return 5"] {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let program = parser.parse_program();
+            check_parser_errors(&parser);
+
+            assert_eq!(
+                program.statements.len(),
+                1,
+                "program.statements does not contain 1 statement. got={}",
+                program.statements.len()
+            );
+        }
+    }
+
     fn test_let_statement(s: &dyn Statement, name: &str) -> bool {
         if s.token_literal() != "let" {
             eprintln!("s.token_literal not 'let'. got={}", s.token_literal());
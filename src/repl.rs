@@ -1,30 +1,412 @@
-use std::io::{BufRead, Write};
+use std::fs;
+use std::io::{self, BufRead, Write};
 
-use crate::{lexer::Lexer, token::TokenType};
+#[cfg(all(unix, feature = "line-editor"))]
+use crate::line_editor::{LineEditor, ReadLine};
+use crate::{
+    ast::Program,
+    lexer::Lexer,
+    parser::Parser,
+    token::{TokenType, KEYWORDS},
+};
 
 const PROMPT: &str = "ali :D >> ";
+const CONTINUATION_PROMPT: &str = "ali :D .. ";
 
-pub fn start<R: BufRead, W: Write>(input: R, mut output: W) {
+const META_HELP: &str = ":tokens <input>  print the tokens of <input>
+:ast <input>     print the parsed statements of <input>
+:env             list the names bound with let
+:load <file>     run a file as if it was typed in
+:reset           forget bindings and pending input
+:help            show this help";
+
+/// Reads entries from `input` until it runs out, writing prompts and results
+/// to `output`.
+///
+/// # Errors
+///
+/// Returns the first error from writing to `output`, such as a broken pipe.
+pub fn start<R: BufRead, W: Write>(input: R, mut output: W) -> io::Result<()> {
     let mut scanner = input.lines();
+    let mut session = Session::default();
 
     loop {
         #[allow(clippy::uninlined_format_args)]
-        write!(output, "{}", PROMPT).unwrap();
-        output.flush().unwrap();
+        write!(output, "{}", session.prompt())?;
+        output.flush()?;
 
         if let Some(Ok(line)) = scanner.next() {
-            let mut lexer = Lexer::new(&line);
-
-            loop {
-                let tok = lexer.next_token();
-                if tok.kind == TokenType::Eof {
-                    break;
-                }
-                #[allow(clippy::uninlined_format_args)]
-                writeln!(output, "{:?}", tok).unwrap();
+            session.feed(&line, &mut output)?;
+        } else {
+            session.finish(&mut output)?;
+            return output.flush();
+        }
+    }
+}
+
+/// Like [`start`], but reads lines through `editor`.
+///
+/// # Errors
+///
+/// Returns the first error from writing to `output`. Errors from the
+/// terminal itself are reported and end the session.
+#[cfg(all(unix, feature = "line-editor"))]
+pub fn start_with_editor<W: Write>(editor: &mut LineEditor, mut output: W) -> io::Result<()> {
+    let mut session = Session::default();
+
+    loop {
+        let completions = session.completions();
+
+        match editor.read_line(session.prompt(), &completions) {
+            Ok(ReadLine::Line(line)) => {
+                editor.add_history(&line);
+                session.feed(&line, &mut output)?;
+                output.flush()?;
+            }
+            Ok(ReadLine::Interrupted) => session.pending.clear(),
+            Ok(ReadLine::Eof) => {
+                session.finish(&mut output)?;
+                return output.flush();
             }
+            // Like end of input, the pending entry is still worth showing
+            Err(err) => {
+                eprintln!("{err}");
+                session.finish(&mut output)?;
+                return output.flush();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Session {
+    // Lines of an entry that is still waiting for its closing delimiters
+    pending: String,
+    // Names bound by let statements so far, offered as completions
+    bindings: Vec<String>,
+}
+
+impl Session {
+    const fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() {
+            PROMPT
         } else {
-            break;
+            CONTINUATION_PROMPT
+        }
+    }
+
+    fn completions(&self) -> Vec<&str> {
+        KEYWORDS
+            .iter()
+            .map(|(keyword, _)| *keyword)
+            .chain(self.bindings.iter().map(String::as_str))
+            .collect()
+    }
+
+    fn feed<W: Write>(&mut self, line: &str, output: &mut W) -> io::Result<()> {
+        if self.pending.is_empty() {
+            if let Some(command) = line.trim_start().strip_prefix(':') {
+                return self.meta_command(command, output);
+            }
+        }
+
+        self.pending.push_str(line);
+        self.pending.push('\n');
+
+        if is_complete(&self.pending) {
+            self.finish(output)?;
+        }
+        Ok(())
+    }
+
+    // Also called when input runs out, since an unfinished entry is still
+    // worth showing
+    fn finish<W: Write>(&mut self, output: &mut W) -> io::Result<()> {
+        let source = std::mem::take(&mut self.pending);
+        self.run(&source, output)
+    }
+
+    fn run<W: Write>(&mut self, source: &str, output: &mut W) -> io::Result<()> {
+        write_tokens(source, output)?;
+        self.remember_bindings(source);
+        Ok(())
+    }
+
+    fn meta_command<W: Write>(&mut self, command: &str, output: &mut W) -> io::Result<()> {
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, arg)| (name, arg.trim()));
+
+        match name {
+            "tokens" => write_tokens(arg, output),
+            "ast" => print_ast(arg, output),
+            "env" => self
+                .bindings
+                .iter()
+                .try_for_each(|binding| writeln!(output, "{binding}")),
+            "load" if arg.is_empty() => writeln!(output, "usage: :load <file>"),
+            "load" => match fs::read_to_string(arg) {
+                Ok(source) => self.run(&source, output),
+                Err(err) => writeln!(output, "could not load {arg}: {err}"),
+            },
+            "reset" => {
+                *self = Self::default();
+                Ok(())
+            }
+            "help" => writeln!(output, "{META_HELP}"),
+            _ => writeln!(output, "unknown command :{name}, try :help"),
+        }
+    }
+
+    // Scans tokens rather than parsed statements, since the parser does not
+    // know function bodies yet and would report the lets inside them too
+    fn remember_bindings(&mut self, source: &str) {
+        let mut lexer = Lexer::new(source);
+        let mut depth = 0_usize;
+        let mut tokens = Vec::new();
+
+        loop {
+            let tok = lexer.next_token();
+            match tok.kind {
+                TokenType::LParen | TokenType::LBrace => depth += 1,
+                TokenType::RParen | TokenType::RBrace => depth = depth.saturating_sub(1),
+                TokenType::Eof => break,
+                _ => {}
+            }
+            tokens.push((depth, tok));
+        }
+
+        for window in tokens.windows(3) {
+            let [(0, let_tok), (_, name), (_, assign)] = window else {
+                continue;
+            };
+            if let_tok.kind == TokenType::Let
+                && name.kind == TokenType::Ident
+                && assign.kind == TokenType::Assign
+                && !self.bindings.contains(&name.literal)
+            {
+                self.bindings.push(name.literal.clone());
+            }
+        }
+    }
+}
+
+/// Input is complete once every `(` and `{` the lexer has seen is closed.
+/// Stray closing delimiters count as complete so the error surfaces right away.
+fn is_complete(input: &str) -> bool {
+    let mut lexer = Lexer::new(input);
+    let mut depth = 0;
+
+    loop {
+        match lexer.next_token().kind {
+            TokenType::LParen | TokenType::LBrace => depth += 1,
+            TokenType::RParen | TokenType::RBrace => depth -= 1,
+            TokenType::Eof => break,
+            _ => {}
         }
     }
+
+    depth <= 0
+}
+
+/// Writes the token dump for `input`, one token per line.
+///
+/// # Errors
+///
+/// Returns any error from writing to `output`.
+pub fn write_tokens<W: Write>(input: &str, output: &mut W) -> io::Result<()> {
+    let mut lexer = Lexer::new(input);
+
+    loop {
+        let tok = lexer.next_token();
+        if tok.kind == TokenType::Eof {
+            return Ok(());
+        }
+        writeln!(output, "{tok:?}")?;
+    }
+}
+
+/// Writes every statement of `program` in its debug form.
+///
+/// # Errors
+///
+/// Returns any error from writing to `output`.
+pub fn write_ast<W: Write>(program: &Program, output: &mut W) -> io::Result<()> {
+    program
+        .statements
+        .iter()
+        .try_for_each(|stmt| writeln!(output, "{stmt:#?}"))
+}
+
+fn print_ast<W: Write>(input: &str, output: &mut W) -> io::Result<()> {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program();
+
+    write_ast(&program, output)?;
+
+    if !parser.errors().is_empty() {
+        writeln!(output, "parser errors:")?;
+        for msg in parser.errors() {
+            writeln!(output, "\t{msg}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str) -> String {
+        let mut output = Vec::new();
+        start(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_is_complete() {
+        let tests = [
+            ("let x = 5;", true),
+            ("let add = fn(x, y) {", false),
+            ("let add = fn(x,", false),
+            ("let add = fn(x, y) {\n x + y;\n};", true),
+            ("if (x) { if (y) { 1 }", false),
+            ("})", true),
+            ("", true),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(is_complete(input), expected, "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_tokens_only_after_input_is_complete() {
+        let output = run("let add = fn(x, y) {\nx + y;\n};\n");
+
+        let expected = format!(
+            "{PROMPT}{CONTINUATION_PROMPT}{CONTINUATION_PROMPT}{}{PROMPT}",
+            [
+                r#"Token { kind: Let, literal: "let" }"#,
+                r#"Token { kind: Ident, literal: "add" }"#,
+                r#"Token { kind: Assign, literal: "=" }"#,
+                r#"Token { kind: Function, literal: "fn" }"#,
+                r#"Token { kind: LParen, literal: "(" }"#,
+                r#"Token { kind: Ident, literal: "x" }"#,
+                r#"Token { kind: Comma, literal: "," }"#,
+                r#"Token { kind: Ident, literal: "y" }"#,
+                r#"Token { kind: RParen, literal: ")" }"#,
+                r#"Token { kind: LBrace, literal: "{" }"#,
+                r#"Token { kind: Ident, literal: "x" }"#,
+                r#"Token { kind: Plus, literal: "+" }"#,
+                r#"Token { kind: Ident, literal: "y" }"#,
+                r#"Token { kind: Semicolon, literal: ";" }"#,
+                r#"Token { kind: RBrace, literal: "}" }"#,
+                r#"Token { kind: Semicolon, literal: ";" }"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_bindings_are_remembered() {
+        let mut session = Session::default();
+        session.feed("let five = 5;", &mut Vec::new()).unwrap();
+        session
+            .feed("let add = fn(x, y) {", &mut Vec::new())
+            .unwrap();
+        assert_eq!(session.bindings, vec!["five"]);
+
+        session.feed("x + y; };", &mut Vec::new()).unwrap();
+        session.feed("let five = 6;", &mut Vec::new()).unwrap();
+        assert_eq!(session.bindings, vec!["five", "add"]);
+        assert!(session.completions().contains(&"add"));
+        assert!(session.completions().contains(&"return"));
+    }
+
+    #[test]
+    fn test_nested_bindings_are_not_remembered() {
+        let mut session = Session::default();
+        session
+            .feed(
+                "let f = fn() { let inner = 1; x; let g = 2; };",
+                &mut Vec::new(),
+            )
+            .unwrap();
+        session.feed("let h = fn(x) {", &mut Vec::new()).unwrap();
+        session.feed("let nested = x;", &mut Vec::new()).unwrap();
+        session.feed("};", &mut Vec::new()).unwrap();
+
+        assert_eq!(session.bindings, vec!["f", "h"]);
+        assert_eq!(meta(&mut session, ":env"), "f\nh\n");
+    }
+
+    fn meta(session: &mut Session, line: &str) -> String {
+        let mut output = Vec::new();
+        session.feed(line, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_meta_commands() {
+        let mut session = Session::default();
+        session.feed("let five = 5;", &mut Vec::new()).unwrap();
+        session.feed("let ten = 10;", &mut Vec::new()).unwrap();
+
+        assert_eq!(
+            meta(&mut session, ":tokens 5;"),
+            "Token { kind: Int, literal: \"5\" }\nToken { kind: Semicolon, literal: \";\" }\n"
+        );
+        assert!(meta(&mut session, ":ast let x = 1;").starts_with("LetStatement {"));
+        assert!(meta(&mut session, ":ast let = 1;")
+            .contains("parser errors:\n\texpected next token to be Ident, got Assign instead"));
+        assert_eq!(meta(&mut session, ":env"), "five\nten\n");
+        assert_eq!(
+            meta(&mut session, ":frobnicate"),
+            "unknown command :frobnicate, try :help\n"
+        );
+
+        meta(&mut session, ":reset");
+        assert_eq!(meta(&mut session, ":env"), "");
+    }
+
+    #[test]
+    fn test_meta_commands_are_plain_input_while_continuing() {
+        let mut session = Session::default();
+        session.feed("let f = fn() {", &mut Vec::new()).unwrap();
+        meta(&mut session, ":reset");
+        assert_eq!(session.pending, "let f = fn() {\n:reset\n");
+    }
+
+    #[test]
+    fn test_load() {
+        let mut session = Session::default();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/monkey/first.monkey");
+
+        let output = meta(&mut session, &format!(":load {path}"));
+        assert!(output.starts_with("Token { kind: Let, literal: \"let\" }\n"));
+        assert_eq!(session.bindings, vec!["five", "ten", "add", "result"]);
+
+        assert!(meta(&mut session, ":load does/not/exist.monkey")
+            .starts_with("could not load does/not/exist.monkey: "));
+    }
+
+    #[test]
+    fn test_write_errors_are_returned() {
+        let mut output = [0; 4];
+        let err = start(&b"let x = 5;\n"[..], &mut output[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn test_unfinished_input_is_flushed_at_eof() {
+        let output = run("fn(x\n");
+
+        assert!(
+            output.ends_with("Token { kind: Ident, literal: \"x\" }\n"),
+            "pending input not printed at eof. got={output:?}"
+        );
+    }
 }
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SymbolScope {
+    Global,
+    Local,
+    Builtin,
+    Free,
+    Function,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: SymbolScope,
+    pub index: usize,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    pub outer: Option<Box<Self>>,
+    store: HashMap<String, Symbol>,
+    num_definitions: usize,
+    pub free_symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a scope for a function literal, nested inside `outer`.
+    #[must_use]
+    pub fn new_enclosed(outer: Self) -> Self {
+        Self {
+            outer: Some(Box::new(outer)),
+            ..Self::default()
+        }
+    }
+
+    /// Closes this scope and hands back the enclosing one.
+    #[must_use]
+    pub fn into_outer(self) -> Option<Self> {
+        self.outer.map(|outer| *outer)
+    }
+
+    #[must_use]
+    pub const fn num_definitions(&self) -> usize {
+        self.num_definitions
+    }
+
+    pub fn define(&mut self, name: &str) -> Symbol {
+        let scope = if self.outer.is_none() {
+            SymbolScope::Global
+        } else {
+            SymbolScope::Local
+        };
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope,
+            index: self.num_definitions,
+        };
+        self.store.insert(name.to_string(), symbol.clone());
+        self.num_definitions += 1;
+        symbol
+    }
+
+    pub fn define_builtin(&mut self, index: usize, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope: SymbolScope::Builtin,
+            index,
+        };
+        self.store.insert(name.to_string(), symbol.clone());
+        symbol
+    }
+
+    /// Binds the name of the function whose body this scope belongs to, so it
+    /// can refer to itself recursively.
+    pub fn define_function_name(&mut self, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope: SymbolScope::Function,
+            index: 0,
+        };
+        self.store.insert(name.to_string(), symbol.clone());
+        symbol
+    }
+
+    fn define_free(&mut self, original: Symbol) -> Symbol {
+        let symbol = Symbol {
+            name: original.name.clone(),
+            scope: SymbolScope::Free,
+            index: self.free_symbols.len(),
+        };
+        self.free_symbols.push(original);
+        self.store.insert(symbol.name.clone(), symbol.clone());
+        symbol
+    }
+
+    /// Looks `name` up in this scope and then in every enclosing one. A local
+    /// of an enclosing function is captured as a free symbol of this scope.
+    pub fn resolve(&mut self, name: &str) -> Option<Symbol> {
+        if let Some(symbol) = self.store.get(name) {
+            return Some(symbol.clone());
+        }
+
+        let symbol = self.outer.as_mut()?.resolve(name)?;
+        match symbol.scope {
+            SymbolScope::Global | SymbolScope::Builtin => Some(symbol),
+            _ => Some(self.define_free(symbol)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, scope: SymbolScope, index: usize) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            scope,
+            index,
+        }
+    }
+
+    #[test]
+    fn test_define() {
+        let mut global = SymbolTable::new();
+        assert_eq!(global.define("a"), symbol("a", SymbolScope::Global, 0));
+        assert_eq!(global.define("b"), symbol("b", SymbolScope::Global, 1));
+
+        let mut first_local = SymbolTable::new_enclosed(global);
+        assert_eq!(first_local.define("c"), symbol("c", SymbolScope::Local, 0));
+        assert_eq!(first_local.define("d"), symbol("d", SymbolScope::Local, 1));
+
+        let mut second_local = SymbolTable::new_enclosed(first_local);
+        assert_eq!(second_local.define("e"), symbol("e", SymbolScope::Local, 0));
+        assert_eq!(second_local.define("f"), symbol("f", SymbolScope::Local, 1));
+    }
+
+    #[test]
+    fn test_resolve_global() {
+        let mut global = SymbolTable::new();
+        global.define("a");
+        global.define("b");
+
+        let expected = [
+            symbol("a", SymbolScope::Global, 0),
+            symbol("b", SymbolScope::Global, 1),
+        ];
+
+        for sym in expected {
+            let result = global.resolve(&sym.name);
+            assert_eq!(result, Some(sym));
+        }
+    }
+
+    #[test]
+    fn test_resolve_nested_local() {
+        let mut global = SymbolTable::new();
+        global.define("a");
+        global.define("b");
+
+        let mut first_local = SymbolTable::new_enclosed(global);
+        first_local.define("c");
+        first_local.define("d");
+
+        let mut second_local = SymbolTable::new_enclosed(first_local);
+        second_local.define("e");
+        second_local.define("f");
+
+        let expected = [
+            symbol("a", SymbolScope::Global, 0),
+            symbol("b", SymbolScope::Global, 1),
+            symbol("c", SymbolScope::Free, 0),
+            symbol("d", SymbolScope::Free, 1),
+            symbol("e", SymbolScope::Local, 0),
+            symbol("f", SymbolScope::Local, 1),
+        ];
+        for sym in expected {
+            assert_eq!(second_local.resolve(&sym.name), Some(sym));
+        }
+
+        assert_eq!(
+            second_local.free_symbols,
+            vec![
+                symbol("c", SymbolScope::Local, 0),
+                symbol("d", SymbolScope::Local, 1),
+            ]
+        );
+
+        let first_local = second_local.into_outer().unwrap();
+        assert_eq!(
+            first_local.num_definitions(),
+            2,
+            "leaving a scope must restore the enclosing one"
+        );
+    }
+
+    #[test]
+    fn test_define_resolve_builtins() {
+        let mut global = SymbolTable::new();
+        let expected = [
+            symbol("a", SymbolScope::Builtin, 0),
+            symbol("c", SymbolScope::Builtin, 1),
+            symbol("e", SymbolScope::Builtin, 2),
+        ];
+        for sym in &expected {
+            global.define_builtin(sym.index, &sym.name);
+        }
+
+        let first_local = SymbolTable::new_enclosed(global);
+        let mut second_local = SymbolTable::new_enclosed(first_local);
+
+        for sym in expected {
+            assert_eq!(second_local.resolve(&sym.name), Some(sym));
+        }
+        assert!(second_local.free_symbols.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_unresolvable_free() {
+        let mut global = SymbolTable::new();
+        global.define("a");
+
+        let mut first_local = SymbolTable::new_enclosed(global);
+        first_local.define("c");
+
+        let mut second_local = SymbolTable::new_enclosed(first_local);
+        second_local.define("e");
+
+        assert_eq!(
+            second_local.resolve("c"),
+            Some(symbol("c", SymbolScope::Free, 0))
+        );
+        for name in ["b", "d"] {
+            assert_eq!(
+                second_local.resolve(name),
+                None,
+                "name {name} resolved, but was expected not to"
+            );
+        }
+    }
+
+    #[test]
+    fn test_shadowing_function_name() {
+        let mut global = SymbolTable::new();
+        global.define_function_name("a");
+        global.define("a");
+
+        assert_eq!(
+            global.resolve("a"),
+            Some(symbol("a", SymbolScope::Global, 0))
+        );
+    }
+}
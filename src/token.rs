@@ -46,19 +46,24 @@ pub enum TokenType {
     NotEq,
 }
 
+/// Keywords and the token each one lexes to.
+pub const KEYWORDS: [(&str, TokenType); 7] = [
+    ("fn", TokenType::Function),
+    ("let", TokenType::Let),
+    ("true", TokenType::True),
+    ("false", TokenType::False),
+    ("if", TokenType::If),
+    ("else", TokenType::Else),
+    ("return", TokenType::Return),
+];
+
 impl TokenType {
     fn from_keyword(ident: &str) -> Self {
         // dbg!(ident);
-        match ident {
-            "fn" => Self::Function,
-            "let" => Self::Let,
-            "true" => Self::True,
-            "false" => Self::False,
-            "if" => Self::If,
-            "else" => Self::Else,
-            "return" => Self::Return,
-            _ => Self::Ident,
-        }
+        KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == ident)
+            .map_or(Self::Ident, |(_, kind)| kind.clone())
     }
 }
 
@@ -66,3 +71,20 @@ impl TokenType {
 pub fn lookup_ident(ident: &str) -> TokenType {
     TokenType::from_keyword(ident)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keywords_are_recognised() {
+        for (keyword, kind) in KEYWORDS {
+            assert_eq!(
+                lookup_ident(keyword),
+                kind,
+                "{keyword} is not looked up as its keyword"
+            );
+        }
+        assert_eq!(lookup_ident("foobar"), TokenType::Ident);
+    }
+}